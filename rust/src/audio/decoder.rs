@@ -14,26 +14,50 @@
 // You should have received a copy of the GNU Lesser General Public License along with this program.
 // If not, see <https://www.gnu.org/licenses/>.
 
-use std::{thread::{JoinHandle, self}, borrow::Cow};
+use std::{thread::{JoinHandle, self}, borrow::Cow, collections::VecDeque};
 
 use anyhow::{Context, anyhow};
-use cpal::traits::StreamTrait;
 use crossbeam::channel::Receiver;
-use symphonia::{core::{formats::{FormatOptions, FormatReader, SeekTo, SeekMode}, meta::MetadataOptions, io::{MediaSourceStream, MediaSource}, probe::Hint, units::{Time, TimeBase}, audio::{AudioBufferRef, AudioBuffer}}, default};
+use symphonia::{core::{formats::{FormatOptions, FormatReader, SeekTo, SeekMode}, meta::MetadataOptions, io::{MediaSourceStream, MediaSource}, probe::Hint, units::TimeBase, audio::{AudioBufferRef, AudioBuffer}}, default};
 
 use crate::utils::{progress_state_stream::*, playback_state_stream::update_playback_state_stream, types::*, callback_stream::update_callback_stream};
 
-use super::{cpal_output::CpalOutput, controls::*};
+use super::{controls::*, dsp::{self, FadeConfig, apply_ramp}, normalization::{NormalizationConfig, ReplayGain, apply_gain}, sink::{Sink, SinkConfig}};
+
+/// How long before the end of the current track to start preloading the
+/// next one in the queue, mirroring librespot's
+/// `PRELOAD_NEXT_TRACK_BEFORE_END_DURATION_MS`.
+const PRELOAD_NEXT_TRACK_BEFORE_END_SECS: u64 = 30;
+
+/// Below this many milliseconds, a forward seek on an unseekable stream is
+/// approximated by decoding forward from the current position instead of
+/// failing outright.
+const SMALL_SEEK_THRESHOLD_MS: u64 = 5_000;
 
 pub struct Decoder
 {
     rx: Receiver<ThreadMessage>,
     state: DecoderState,
-    cpal_output: Option<CpalOutput>,
+    /// Which `Sink` implementation to build the next time a source is opened.
+    sink_config: SinkConfig,
+    sink: Option<Box<dyn Sink>>,
+    /// ReplayGain / loudness normalization settings.
+    normalization: NormalizationConfig,
+    /// Crossfade/fade-in/fade-out durations.
+    fade: FadeConfig,
+    /// `(total_frames, frames_remaining)` while crossfading into the
+    /// preloaded next track. `None` when not crossfading.
+    crossfade_state: Option<(u64, u64)>,
+    /// `(total_frames, frames_remaining)` while ramping up after a fade-in
+    /// armed at the start of a track (see `arm_fade_in`). Tracked the same way as `crossfade_state` so the
+    /// ramp ratio is computed across the whole fade-in, not per buffer.
+    fade_in_state: Option<(u64, u64)>,
     playback: Option<Playback>,
     preload_playback: Option<Playback>,
     /// The `JoinHandle` for the thread that preloads a file.
-    preload_thread: Option<JoinHandle<anyhow::Result<Playback>>>
+    preload_thread: Option<JoinHandle<anyhow::Result<Playback>>>,
+    /// Sources queued up to play after the current one, for gapless playback.
+    queue: VecDeque<Box<dyn MediaSource>>
 }
 
 impl Decoder
@@ -46,10 +70,16 @@ impl Decoder
         Decoder {
             rx,
             state: DecoderState::Idle,
-            cpal_output: None,
+            sink_config: SinkConfig::Cpal,
+            sink: None,
+            normalization: NormalizationConfig::default(),
+            fade: FadeConfig::default(),
+            crossfade_state: None,
+            fade_in_state: None,
             playback: None,
             preload_playback: None,
-            preload_thread: None
+            preload_thread: None,
+            queue: VecDeque::new()
         }
     }
 
@@ -121,41 +151,71 @@ impl Decoder
             {
                 ThreadMessage::Dispose => return Ok(true),
                 ThreadMessage::Open(source) => {
-                    self.cpal_output = None;
+                    self.sink = None;
                     self.playback = Some(Self::open(source)?);
+                    self.arm_fade_in();
+                },
+                ThreadMessage::SetSink(sink_config) => {
+                    self.sink_config = sink_config;
+                    self.sink = None;
+                },
+                ThreadMessage::SetNormalization(normalization) => {
+                    self.normalization = normalization;
+                },
+                ThreadMessage::SetFade(fade) => {
+                    self.fade = fade;
                 },
                 ThreadMessage::Play => {
                     self.state = DecoderState::Playing;
 
-                    // Windows handles play/pause differently.
-                    if cfg!(not(target_os = "windows")) {
-                        if let Some(cpal_output) = &self.cpal_output {
-                            cpal_output.stream.play()?;
-                        }
+                    // Every platform, including Windows, dispatches through
+                    // the `Sink` trait now instead of a platform-specific
+                    // branch touching the underlying output directly.
+                    if let Some(sink) = &mut self.sink {
+                        sink.play()?;
                     }
                 },
                 ThreadMessage::Pause => {
                     self.state = DecoderState::Paused;
 
-                    // Windows handles play/pause differently.
-                    if cfg!(not(target_os = "windows")) {
-                        if let Some(cpal_output) = &self.cpal_output {
-                            cpal_output.stream.pause()?;
-                        }
+                    // Every platform, including Windows, dispatches through
+                    // the `Sink` trait now instead of a platform-specific
+                    // branch touching the underlying output directly.
+                    if let Some(sink) = &mut self.sink {
+                        sink.pause()?;
                     }
                 },
                 ThreadMessage::Stop => {
+                    // Ramp the next, not-yet-emitted decoded buffer down to
+                    // silence instead of cutting playback off abruptly. This
+                    // decodes fresh audio from the current reader position
+                    // rather than replaying whatever was already handed to
+                    // the sink, which would otherwise sound like a
+                    // stutter/repeat right before silence.
+                    if self.fade.fade_out_ms > 0 {
+                        if let Some(fade_out) = self.decode_fade_out_buffer() {
+                            if let Some(sink) = self.sink.as_mut() {
+                                sink.write(AudioBufferRef::F32(Cow::Owned(fade_out)));
+                            }
+                        }
+                    }
+
+                    if let Some(sink) = self.sink.as_mut() {
+                        sink.flush();
+                    }
+
                     self.state = DecoderState::Idle;
-                    self.cpal_output = None;
+                    self.sink = None;
                     self.playback = None;
+                    self.crossfade_state = None;
                 },
                 // When the device is changed/disconnected,
                 // then we should reestablish a connection.
-                // To make a new connection, dispose of the current cpal_output
+                // To make a new connection, dispose of the current sink
                 // and pause playback. Once the user is ready, they can start
                 // playback themselves.
                 ThreadMessage::DeviceChanged => {
-                    self.cpal_output = None;
+                    self.sink = None;
                     crate::Player::internal_pause();
                 },
                 ThreadMessage::Preload(source) => {
@@ -164,6 +224,14 @@ impl Decoder
                     let handle = Self::preload(source);
                     self.preload_thread = Some(handle);
                 },
+                // Append a single source to the gapless queue.
+                ThreadMessage::Enqueue(source) => {
+                    self.queue.push_back(source);
+                },
+                // Replace the entire gapless queue.
+                ThreadMessage::SetQueue(sources) => {
+                    self.queue = VecDeque::from(sources);
+                },
                 ThreadMessage::PlayPreload => {
                     if self.preload_playback.is_none() {
                         return Ok(false);
@@ -171,9 +239,10 @@ impl Decoder
 
                     crate::Player::internal_play();
 
-                    self.cpal_output = None;
+                    self.sink = None;
                     self.playback = self.preload_playback.take();
                     IS_FILE_PRELOADED.store(false, std::sync::atomic::Ordering::SeqCst);
+                    self.arm_fade_in();
                 }
             }
         }
@@ -181,7 +250,7 @@ impl Decoder
         Ok(false)
     }
 
-    /// Decodes a packet and writes to `cpal_output`.
+    /// Decodes a packet and writes to the sink.
     /// 
     /// Returns `true` when the playback is complete.
     /// Returns `false` otherwise.
@@ -192,41 +261,87 @@ impl Decoder
 
         // If there is audio already decoded from preloading,
         // then output that instead.
-        if let Some(preload) = playback.preload.take() {
-            // Write the decoded packet to CPAL.
-            if self.cpal_output.is_none()
+        if let Some(mut preload) = playback.preload.take() {
+            // Write the decoded packet to the sink.
+            if self.sink.is_none()
             {
                 let spec = *preload.spec();
                 let duration = preload.capacity() as u64;
-                self.cpal_output.replace(CpalOutput::new(spec, duration)?);
+                self.sink.replace(self.sink_config.build(spec, duration)?);
+            }
+
+            let gain = self.normalization.linear_gain(&playback.replay_gain);
+            apply_gain(&mut preload, gain);
+
+            // The preloaded buffer is the first one of its track, so the
+            // fade-in armed for that track starts here.
+            if let Some((total_frames, frames_remaining)) = self.fade_in_state {
+                let frames_here = preload.frames() as u64;
+                let elapsed = total_frames.saturating_sub(frames_remaining);
+                let start_gain = elapsed as f32 / total_frames.max(1) as f32;
+                let end_gain = (elapsed + frames_here).min(total_frames) as f32 / total_frames.max(1) as f32;
+                apply_ramp(&mut preload, start_gain, end_gain);
+                self.fade_in_state = frames_remaining.checked_sub(frames_here)
+                    .filter(|&r| r > 0)
+                    .map(|r| (total_frames, r));
             }
 
             let buffer_ref = AudioBufferRef::F32(Cow::Borrowed(&preload));
-            self.cpal_output.as_mut().unwrap().write(buffer_ref);
+            self.sink.as_mut().unwrap().write(buffer_ref);
 
             return Ok(false);
         }
 
-        let seek_ts: u64 = if let Some(seek_ts) = *SEEK_TS.read().unwrap()
-        {
-            let seek_to = SeekTo::Time { time: Time::from(seek_ts), track_id: Some(playback.track_id) };
-            match playback.reader.seek(SeekMode::Coarse, seek_to)
-            {
-                Ok(seeked_to) => seeked_to.required_ts,
-                Err(_) => 0
-            }
-        } else { 0 };
-
-        // Clean up seek stuff.
-        if SEEK_TS.read().unwrap().is_some()
+        // `SEEK_TS` holds the seek target in milliseconds. Convert it to a
+        // frame timestamp using the track's sample rate so the seek lands
+        // on an exact frame instead of a seconds-granular boundary.
+        if let Some(seek_ms) = *SEEK_TS.read().unwrap()
         {
             *SEEK_TS.write().unwrap() = None;
-            playback.decoder.reset();
-            // Clear the ring buffer which prevents the writer
-            // from blocking.
-            if let Some(cpal_output) = self.cpal_output.as_ref() {
-                cpal_output.ring_buffer_reader.skip_all();
+
+            let target_frame = seek_ms * playback.sample_rate as u64 / 1000;
+            let seek_to = SeekTo::TimeStamp { ts: target_frame, track_id: playback.track_id };
+
+            match playback.reader.seek(SeekMode::Accurate, seek_to) {
+                Ok(seeked_to) => {
+                    playback.decoder.reset();
+                    // Clear the ring buffer which prevents the writer
+                    // from blocking.
+                    if let Some(sink) = self.sink.as_mut() {
+                        sink.flush();
+                    }
+                    // The reader may land slightly before the requested frame;
+                    // drop the leading frames of the next decoded buffer so
+                    // output starts exactly at `target_frame`.
+                    playback.frames_to_drop = target_frame.saturating_sub(seeked_to.actual_ts);
+                },
+                // Some sources (ex: non-seekable streams) can't seek at all.
+                // Approximate a small *forward* seek by decoding ahead from
+                // wherever we currently are and dropping frames until we
+                // reach the target. The "small" threshold is a distance
+                // from `last_frame_ts` (where we actually are), not from
+                // the seek's absolute target timestamp - otherwise a small
+                // forward seek late in a long stream would wrongly fail the
+                // threshold, and a large backward seek to an early absolute
+                // timestamp would wrongly pass it. A backward seek is never
+                // approximated this way, since there's no way to un-decode
+                // already-consumed data on an unseekable stream.
+                Err(_) if !playback.reader.seekable() && target_frame >= playback.last_frame_ts => {
+                    let forward_distance = target_frame - playback.last_frame_ts;
+                    let threshold_frames = SMALL_SEEK_THRESHOLD_MS * playback.sample_rate as u64 / 1000;
+
+                    if forward_distance <= threshold_frames {
+                        playback.decoder.reset();
+                        playback.frames_to_drop = forward_distance;
+                    } else {
+                        update_callback_stream(Callback::SeekError);
+                    }
+                },
+                Err(_) => {
+                    update_callback_stream(Callback::SeekError);
+                }
             }
+
             return Ok(false);
         }
 
@@ -244,17 +359,50 @@ impl Decoder
                     return Ok(false);
                 }
 
+                // If the next track has already been preloaded, swap it in
+                // instead of stopping, giving a gapless transition. This can
+                // race a crossfade that hasn't finished yet (ex: an
+                // imprecise container-reported duration), so clear
+                // `crossfade_state` here too, not just in `mix_crossfade`'s
+                // own completion paths, or the next crossfade would start
+                // mid-ramp at a stale volume.
+                if let Some(next_playback) = self.preload_playback.take() {
+                    self.crossfade_state = None;
+                    self.playback = Some(next_playback);
+                    IS_FILE_PRELOADED.store(false, std::sync::atomic::Ordering::SeqCst);
+                    self.arm_fade_in();
+                    update_callback_stream(Callback::TrackChanged);
+                    return Ok(false);
+                }
+
                 return Ok(true);
             }
         };
 
         if packet.track_id() != playback.track_id { return Ok(false); }
 
+        playback.last_frame_ts = packet.ts();
+
         let decoded = playback.decoder.decode(&packet)
             .context("Could not decode audio packet.")?;
 
-        if packet.ts() < seek_ts { return Ok(false); }
-        
+        // Drop leading frames left over from a sample-accurate seek so
+        // output starts exactly at the requested frame.
+        let decoded = if playback.frames_to_drop > 0 {
+            let frames_in_buffer = decoded.frames() as u64;
+
+            if playback.frames_to_drop >= frames_in_buffer {
+                playback.frames_to_drop -= frames_in_buffer;
+                return Ok(false);
+            }
+
+            let drop = playback.frames_to_drop as usize;
+            playback.frames_to_drop = 0;
+            AudioBufferRef::F32(Cow::Owned(Self::drop_leading_frames(decoded, drop)))
+        } else {
+            decoded
+        };
+
         let position = if let Some(timebase) = playback.timebase {
             timebase.calc_time(packet.ts()).seconds
         } else {
@@ -270,26 +418,193 @@ impl Decoder
         update_progress_state_stream(progress);
         *PROGRESS.write().unwrap() = progress;
 
-        // Write the decoded packet to CPAL.
-        if self.cpal_output.is_none()
+        // Once we're within the preload window of the end of this track,
+        // start preloading the next queued one so it's ready for a
+        // gapless swap when this one reaches EOF.
+        if self.preload_thread.is_none()
+            && self.preload_playback.is_none()
+            && !IS_FILE_PRELOADED.load(std::sync::atomic::Ordering::SeqCst)
+            && playback.duration.saturating_sub(position) <= PRELOAD_NEXT_TRACK_BEFORE_END_SECS
+        {
+            if let Some(next_source) = self.queue.pop_front() {
+                self.preload_thread = Some(Self::preload(next_source));
+            }
+        }
+
+        // Once the next track is preloaded and we've entered its crossfade
+        // window, start ramping between the two. Compared at frame
+        // precision (not the whole-second `duration`/`position`), since
+        // truncating to seconds would permanently disable crossfading for
+        // any `crossfade_ms` under 1000.
+        if self.crossfade_state.is_none() && self.fade.crossfade_ms > 0 && self.preload_playback.is_some()
+        {
+            let crossfade_frames = self.fade.crossfade_frames(playback.sample_rate);
+            let remaining_frames = playback.duration_ts.map(|total| total.saturating_sub(playback.last_frame_ts));
+
+            if remaining_frames.map(|remaining| remaining <= crossfade_frames).unwrap_or(false) {
+                self.crossfade_state = Some((crossfade_frames, crossfade_frames));
+            }
+        }
+
+        // Apply ReplayGain / master volume.
+        let gain = self.normalization.linear_gain(&playback.replay_gain);
+        let mut outgoing = AudioBuffer::new(decoded.capacity() as u64, *decoded.spec());
+        decoded.convert(&mut outgoing);
+        apply_gain(&mut outgoing, gain);
+
+        // Ramp up from silence for the configured fade-in duration. Gain is
+        // computed from the elapsed/total frame ratio across the whole
+        // fade-in window (like `crossfade_state`), not reset per buffer, so
+        // a multi-second fade-in ramps smoothly instead of completing (and
+        // restarting) within every decoded buffer.
+        if let Some((total_frames, frames_remaining)) = self.fade_in_state {
+            let frames_here = outgoing.frames() as u64;
+            let elapsed = total_frames.saturating_sub(frames_remaining);
+            let start_gain = elapsed as f32 / total_frames.max(1) as f32;
+            let end_gain = (elapsed + frames_here).min(total_frames) as f32 / total_frames.max(1) as f32;
+            apply_ramp(&mut outgoing, start_gain, end_gain);
+            self.fade_in_state = frames_remaining.checked_sub(frames_here)
+                .filter(|&r| r > 0)
+                .map(|r| (total_frames, r));
+        }
+
+        let output = self.mix_crossfade(outgoing)?;
+
+        if self.sink.is_none()
         {
-            let spec = *decoded.spec();
-            let duration = decoded.capacity() as u64;
-            self.cpal_output.replace(CpalOutput::new(spec, duration)?);
+            let spec = *output.spec();
+            let duration = output.capacity() as u64;
+            self.sink.replace(self.sink_config.build(spec, duration)?);
         }
 
-        self.cpal_output.as_mut().unwrap().write(decoded);
+        self.sink.as_mut().unwrap().write(AudioBufferRef::F32(Cow::Owned(output)));
 
         Ok(false)
     }
 
+    /// If a crossfade is in progress, mixes `outgoing` with a freshly
+    /// decoded buffer from the preloaded next track and returns the mixed
+    /// result, swapping `playback` over to the next track once the
+    /// crossfade window elapses. Returns `outgoing` unmixed when no
+    /// crossfade is in progress.
+    fn mix_crossfade(&mut self, outgoing: AudioBuffer<f32>) -> anyhow::Result<AudioBuffer<f32>>
+    {
+        let Some((total_frames, frames_remaining)) = self.crossfade_state else {
+            return Ok(outgoing);
+        };
+
+        let Some(incoming_playback) = self.preload_playback.as_mut() else {
+            self.crossfade_state = None;
+            return Ok(outgoing);
+        };
+
+        let mut incoming = if let Some(buf) = incoming_playback.preload.take() {
+            buf
+        } else {
+            match incoming_playback.reader.next_packet() {
+                Ok(packet) => {
+                    let incoming_ref = incoming_playback.decoder.decode(&packet)
+                        .context("Could not decode audio packet.")?;
+                    let mut buf = AudioBuffer::new(incoming_ref.capacity() as u64, *incoming_ref.spec());
+                    incoming_ref.convert(&mut buf);
+                    buf
+                },
+                // The incoming track is somehow already exhausted; fall back
+                // to a plain gapless swap on the next EOF.
+                Err(_) => {
+                    self.crossfade_state = None;
+                    return Ok(outgoing);
+                }
+            }
+        };
+
+        // Apply the incoming track's own ReplayGain/normalization before
+        // mixing, the same as `outgoing` already had applied before this
+        // was called, so the level doesn't jump once the crossfade
+        // finishes and gain correction starts applying on its own.
+        let incoming_gain = self.normalization.linear_gain(&incoming_playback.replay_gain);
+        apply_gain(&mut incoming, incoming_gain);
+
+        // Both streams must share a spec and frame count to be mixed
+        // sample-by-sample. Resample the incoming buffer to match the
+        // outgoing track's spec/frame count when they differ (ex: tracks
+        // recorded at different sample rates), rather than bailing out of
+        // crossfading for what will be the common case in a mixed playlist.
+        let incoming = if *outgoing.spec() != *incoming.spec() || outgoing.frames() != incoming.frames() {
+            dsp::resample(&incoming, *outgoing.spec(), outgoing.frames())
+        } else {
+            incoming
+        };
+
+        let elapsed = total_frames.saturating_sub(frames_remaining);
+        let frames_here = outgoing.frames() as u64;
+        let t0 = elapsed as f32 / total_frames.max(1) as f32;
+        let t1 = (elapsed + frames_here).min(total_frames) as f32 / total_frames.max(1) as f32;
+
+        let mixed = dsp::mix(&outgoing, &incoming, (1.0 - t0, 1.0 - t1), (t0, t1));
+
+        match frames_remaining.checked_sub(frames_here).filter(|&r| r > 0) {
+            Some(remaining) => {
+                self.crossfade_state = Some((total_frames, remaining));
+                Ok(mixed)
+            },
+            None => {
+                // Crossfade complete; finish handing playback over to the
+                // track we were fading into.
+                self.crossfade_state = None;
+                self.playback = self.preload_playback.take();
+                IS_FILE_PRELOADED.store(false, std::sync::atomic::Ordering::SeqCst);
+                update_callback_stream(Callback::TrackChanged);
+                Ok(mixed)
+            }
+        }
+    }
+
+    /// Decodes the next packet of the current track, cuts it to at most
+    /// `fade_out_ms` worth of frames and ramps that down from full volume to
+    /// silence, for use as the last thing written before a `Stop`. Returns
+    /// `None` if there's no current playback or the reader has nothing left
+    /// to decode.
+    fn decode_fade_out_buffer(&mut self) -> Option<AudioBuffer<f32>>
+    {
+        let playback = self.playback.as_mut()?;
+        let packet = playback.reader.next_packet().ok()?;
+
+        if packet.track_id() != playback.track_id { return None; }
+
+        let decoded = playback.decoder.decode(&packet).ok()?;
+        let mut fade_out = AudioBuffer::new(decoded.capacity() as u64, *decoded.spec());
+        decoded.convert(&mut fade_out);
+
+        let max_frames = self.fade.fade_out_frames(playback.sample_rate) as usize;
+        if fade_out.frames() > max_frames {
+            fade_out.truncate(max_frames);
+        }
+
+        apply_ramp(&mut fade_out, 1.0, 0.0);
+        Some(fade_out)
+    }
+
+    /// Arms `fade_in_state` for a genuine track start (a fresh `Open`, or a
+    /// gapless/preloaded advance to the next track) so the ramp-up only
+    /// ever applies at the start of a track, not to every resume from
+    /// `Pause`.
+    fn arm_fade_in(&mut self)
+    {
+        if self.fade.fade_in_ms == 0 { return; }
+
+        let sample_rate = self.playback.as_ref().map(|p| p.sample_rate).unwrap_or(44_100);
+        let total_frames = self.fade.fade_in_ms * sample_rate as u64 / 1000;
+        self.fade_in_state = Some((total_frames, total_frames));
+    }
+
     /// Called when the file is finished playing.
-    /// 
-    /// Flushes `cpal_output` and sends a `Done` message to Dart.
+    ///
+    /// Flushes the sink and sends a `Done` message to Dart.
     fn finish_playback(&mut self)
     {
-        if let Some(cpal_output) = self.cpal_output.as_mut() {
-            cpal_output.flush();
+        if let Some(sink) = self.sink.as_mut() {
+            sink.flush();
         }
 
         // Send the done message once cpal finishes flushing.
@@ -304,19 +619,29 @@ impl Decoder
 
     /// Opens the given source for playback. Returns a `Playback`
     /// for the source.
+    ///
+    /// `source` may be backed by a local file or, via `HttpSource`, a
+    /// remote URL streamed over HTTP.
     fn open(source: Box<dyn MediaSource>) -> anyhow::Result<Playback>
     {
         let mss = MediaSourceStream::new(source, Default::default());
         let format_options = FormatOptions { enable_gapless: true, ..Default::default() };
         let metadata_options: MetadataOptions = Default::default();
 
-        let probed = default::get_probe().format(
+        let mut probed = default::get_probe().format(
             &Hint::new(),
             mss,
             &format_options,
             &metadata_options
         ).context("Failed to create format reader.")?;
 
+        // Read the `REPLAYGAIN_*` tags, if any, off of the container or
+        // stream metadata so normalization can be applied during playback.
+        let replay_gain = probed.format.metadata().skip_to_latest()
+            .map(ReplayGain::parse)
+            .or_else(|| probed.metadata.get().as_ref().and_then(|m| m.current().map(ReplayGain::parse)))
+            .unwrap_or_default();
+
         let reader = probed.format;
 
         let track = reader.default_track()
@@ -336,12 +661,20 @@ impl Decoder
             0
         };
 
+        let sample_rate = track.codec_params.sample_rate
+            .context("Cannot start playback. The track has no sample rate.")?;
+
         Ok(Playback {
             reader,
             decoder,
             track_id,
             timebase,
             duration,
+            duration_ts: ts,
+            sample_rate,
+            frames_to_drop: 0,
+            last_frame_ts: 0,
+            replay_gain,
             preload: None
         })
     }
@@ -368,7 +701,24 @@ impl Decoder
         })
     }
 
-    /// Polls the `preload_thread`. If it is finished, the 
+    /// Returns a copy of `decoded` with the first `drop` frames removed,
+    /// used to land exactly on the requested frame after a seek.
+    fn drop_leading_frames(decoded: AudioBufferRef<'_>, drop: usize) -> AudioBuffer<f32>
+    {
+        let spec = *decoded.spec();
+        let mut buf = AudioBuffer::new(decoded.capacity() as u64, spec);
+        decoded.convert(&mut buf);
+
+        let remaining = buf.frames().saturating_sub(drop);
+        for channel in 0..spec.channels.count() {
+            buf.chan_mut(channel).copy_within(drop.., 0);
+        }
+        buf.truncate(remaining);
+
+        buf
+    }
+
+    /// Polls the `preload_thread`. If it is finished, the
     /// preloaded file is then placed in `preload_playback`.
     fn poll_preload_thread(&mut self) -> anyhow::Result<()>
     {
@@ -423,6 +773,21 @@ struct Playback
     decoder: Box<dyn symphonia::core::codecs::Decoder>,
     timebase: Option<TimeBase>,
     duration: u64,
+    /// The track's total length in frames, if known. Used (alongside
+    /// `last_frame_ts`) to detect the crossfade window at frame precision
+    /// instead of rounding through whole-second `duration`/`position`.
+    duration_ts: Option<u64>,
+    /// The track's sample rate, used to convert a seek target in
+    /// milliseconds into a frame timestamp.
+    sample_rate: u32,
+    /// Frames still left to discard from upcoming decoded buffers,
+    /// left over from a sample-accurate seek.
+    frames_to_drop: u64,
+    /// The timestamp (in frames) of the last packet decoded, used to
+    /// compute how far ahead to read when a source can't seek directly.
+    last_frame_ts: u64,
+    /// The `REPLAYGAIN_*` tags read from the source, if any.
+    replay_gain: ReplayGain,
     /// A buffer of already decoded samples.
     preload: Option<AudioBuffer<f32>>
 }
\ No newline at end of file
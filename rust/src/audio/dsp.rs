@@ -0,0 +1,187 @@
+// This file is a part of simple_audio
+// Copyright (c) 2022-2023 Erikas Taroza <erikastaroza@gmail.com>
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public License as
+// published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with this program.
+// If not, see <https://www.gnu.org/licenses/>.
+
+use symphonia::core::audio::{AudioBuffer, SignalSpec};
+
+/// Durations (in milliseconds) for the fade/crossfade DSP chain that sits
+/// between the decoder and the sink.
+#[derive(Clone, Copy)]
+pub struct FadeConfig
+{
+    /// How long the outgoing and incoming tracks overlap when transitioning
+    /// between queued tracks. `0` disables crossfading (a plain gapless swap).
+    pub crossfade_ms: u64,
+    /// Length of the ramp-up from silence at the start of a track (a fresh
+    /// open, or a gapless/preloaded advance); it spans as many decoded
+    /// buffers as it needs. `0` disables it.
+    pub fade_in_ms: u64,
+    /// Length of the ramp-down applied when `ThreadMessage::Stop` arrives,
+    /// so playback doesn't cut off abruptly. The ramp covers one decoded
+    /// packet at most, so a packet shorter than this gives a shorter fade.
+    /// `0` disables it.
+    pub fade_out_ms: u64
+}
+
+impl Default for FadeConfig
+{
+    fn default() -> Self
+    {
+        FadeConfig { crossfade_ms: 0, fade_in_ms: 0, fade_out_ms: 50 }
+    }
+}
+
+impl FadeConfig
+{
+    pub fn crossfade_frames(&self, sample_rate: u32) -> u64
+    {
+        self.crossfade_ms * sample_rate as u64 / 1000
+    }
+
+    pub fn fade_out_frames(&self, sample_rate: u32) -> u64
+    {
+        self.fade_out_ms * sample_rate as u64 / 1000
+    }
+}
+
+/// Scales every sample in `buffer` by a linear ramp that goes from
+/// `start_gain` to `end_gain` over the buffer's frames. Used for both
+/// crossfade ramps and the fade-out on stop.
+pub fn apply_ramp(buffer: &mut AudioBuffer<f32>, start_gain: f32, end_gain: f32)
+{
+    let frames = buffer.frames();
+    if frames == 0 { return; }
+
+    for channel in 0..buffer.spec().channels.count() {
+        let plane = buffer.chan_mut(channel);
+        for (i, sample) in plane.iter_mut().enumerate() {
+            let t = i as f32 / frames as f32;
+            *sample *= start_gain + (end_gain - start_gain) * t;
+        }
+    }
+}
+
+/// Resamples `buffer` to `target_spec`, producing exactly `target_frames`
+/// frames, using linear interpolation for the sample-rate change and
+/// simple channel remapping (extra output channels reuse the last input
+/// channel; missing ones are dropped). Lets two tracks recorded at
+/// different sample rates/channel layouts still be crossfaded sample-by-
+/// sample via `mix`, instead of requiring an exact spec match up front.
+pub fn resample(buffer: &AudioBuffer<f32>, target_spec: SignalSpec, target_frames: usize) -> AudioBuffer<f32>
+{
+    let src_frames = buffer.frames();
+    let src_channels = buffer.spec().channels.count();
+    let dst_channels = target_spec.channels.count();
+
+    let mut out = AudioBuffer::<f32>::new(target_frames.max(1) as u64, target_spec);
+    out.render_reserved(Some(target_frames));
+
+    for dst_channel in 0..dst_channels {
+        let src_channel = dst_channel.min(src_channels.saturating_sub(1));
+        let src_plane = buffer.chan(src_channel);
+
+        for i in 0..target_frames {
+            let src_pos = if target_frames > 1 {
+                i as f64 * (src_frames.saturating_sub(1)) as f64 / (target_frames - 1) as f64
+            } else {
+                0.0
+            };
+
+            let i0 = src_pos.floor() as usize;
+            let frac = (src_pos - i0 as f64) as f32;
+            let s0 = src_plane.get(i0).copied().unwrap_or(0.0);
+            let s1 = src_plane.get(i0 + 1).copied().unwrap_or(s0);
+
+            out.chan_mut(dst_channel)[i] = s0 + (s1 - s0) * frac;
+        }
+    }
+
+    out
+}
+
+/// Mixes `incoming` on top of `outgoing`, sample-by-sample, after ramping
+/// the outgoing buffer down by `outgoing_gain` (1 -> 0 over the crossfade
+/// window) and the incoming buffer up by `incoming_gain` (0 -> 1). Both
+/// buffers must already share a `SignalSpec` and frame count; callers
+/// should resample/pad mismatched buffers before calling this.
+pub fn mix(outgoing: &AudioBuffer<f32>, incoming: &AudioBuffer<f32>, outgoing_gain: (f32, f32), incoming_gain: (f32, f32)) -> AudioBuffer<f32>
+{
+    let mut mixed = outgoing.clone();
+    apply_ramp(&mut mixed, outgoing_gain.0, outgoing_gain.1);
+
+    let mut incoming = incoming.clone();
+    apply_ramp(&mut incoming, incoming_gain.0, incoming_gain.1);
+
+    let channels = mixed.spec().channels.count();
+    for channel in 0..channels {
+        let incoming_plane = incoming.chan(channel).to_vec();
+        let mixed_plane = mixed.chan_mut(channel);
+        for (dst, src) in mixed_plane.iter_mut().zip(incoming_plane) {
+            *dst += src;
+        }
+    }
+
+    mixed
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use symphonia::core::audio::{Channels, SignalSpec};
+
+    fn buffer_of(value: f32, frames: usize) -> AudioBuffer<f32>
+    {
+        let spec = SignalSpec::new(44_100, Channels::FRONT_LEFT);
+        let mut buffer = AudioBuffer::<f32>::new(frames as u64, spec);
+        buffer.render_reserved(Some(frames));
+        for sample in buffer.chan_mut(0).iter_mut() { *sample = value; }
+        buffer
+    }
+
+    #[test]
+    fn apply_ramp_scales_first_and_last_frame()
+    {
+        let mut buffer = buffer_of(1.0, 4);
+        apply_ramp(&mut buffer, 0.0, 1.0);
+        let samples = buffer.chan(0);
+        assert_eq!(samples[0], 0.0);
+        assert!((samples[3] - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn apply_ramp_is_noop_on_empty_buffer()
+    {
+        let mut buffer = buffer_of(1.0, 0);
+        apply_ramp(&mut buffer, 0.0, 1.0);
+        assert_eq!(buffer.frames(), 0);
+    }
+
+    #[test]
+    fn mix_sums_ramped_outgoing_and_incoming()
+    {
+        let outgoing = buffer_of(1.0, 2);
+        let incoming = buffer_of(1.0, 2);
+
+        let mixed = mix(&outgoing, &incoming, (1.0, 0.0), (0.0, 1.0));
+        let samples = mixed.chan(0);
+
+        // At frame 0: outgoing is at full gain (1.0), incoming at none (0.0).
+        assert!((samples[0] - 1.0).abs() < 1e-6);
+        // At the last frame: the ramps stop one step short of their end
+        // gains, so outgoing is at 0.5 and incoming at 0.5.
+        assert!((samples[1] - 1.0).abs() < 1e-6);
+    }
+}
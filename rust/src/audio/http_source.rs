@@ -0,0 +1,396 @@
+// This file is a part of simple_audio
+// Copyright (c) 2022-2023 Erikas Taroza <erikastaroza@gmail.com>
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public License as
+// published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with this program.
+// If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io::{Read, Seek, SeekFrom, self},
+    sync::{Arc, Condvar, Mutex},
+    thread
+};
+
+use symphonia::core::io::MediaSource;
+
+/// How much to fetch per request while reading ahead sequentially, instead
+/// of pulling the entire remaining file in one shot.
+const STREAM_CHUNK_BYTES: u64 = 1024 * 1024;
+
+/// A half-open `[start, end)` byte range. An `end` of `u64::MAX` is sent to
+/// the server as an open-ended HTTP range.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct ByteRange { start: u64, end: u64 }
+
+/// A request handed to the fetch thread. `continuous` marks a sequential
+/// read-ahead request: once it completes, the fetch thread enqueues the
+/// next chunk automatically, so playback never stalls waiting on a
+/// whole-file download.
+struct FetchRequest
+{
+    range: ByteRange,
+    continuous: bool
+}
+
+/// A set of downloaded byte ranges, merging adjacent/overlapping entries
+/// as they're inserted so membership checks stay cheap.
+#[derive(Default)]
+struct RangeSet { ranges: Vec<ByteRange> }
+
+impl RangeSet
+{
+    fn insert(&mut self, mut range: ByteRange)
+    {
+        self.ranges.retain(|existing| {
+            // Merge anything touching or overlapping the new range into it
+            // instead of keeping it as a separate entry.
+            let touches = existing.start <= range.end && range.start <= existing.end;
+            if touches {
+                range.start = range.start.min(existing.start);
+                range.end = range.end.max(existing.end);
+            }
+            !touches
+        });
+
+        self.ranges.push(range);
+    }
+
+    /// Returns `true` if `[start, end)` is fully covered by a downloaded range.
+    fn contains(&self, start: u64, end: u64) -> bool
+    {
+        self.ranges.iter().any(|r| r.start <= start && end <= r.end)
+    }
+}
+
+#[cfg(test)]
+mod range_set_tests
+{
+    use super::*;
+
+    #[test]
+    fn contains_is_false_when_empty()
+    {
+        let ranges = RangeSet::default();
+        assert!(!ranges.contains(0, 10));
+    }
+
+    #[test]
+    fn contains_true_within_a_single_range()
+    {
+        let mut ranges = RangeSet::default();
+        ranges.insert(ByteRange { start: 0, end: 100 });
+        assert!(ranges.contains(10, 50));
+        assert!(!ranges.contains(50, 150));
+    }
+
+    #[test]
+    fn insert_merges_overlapping_ranges()
+    {
+        let mut ranges = RangeSet::default();
+        ranges.insert(ByteRange { start: 0, end: 50 });
+        ranges.insert(ByteRange { start: 40, end: 100 });
+        assert_eq!(ranges.ranges.len(), 1);
+        assert!(ranges.contains(0, 100));
+    }
+
+    #[test]
+    fn insert_merges_adjacent_ranges()
+    {
+        let mut ranges = RangeSet::default();
+        ranges.insert(ByteRange { start: 0, end: 50 });
+        ranges.insert(ByteRange { start: 50, end: 100 });
+        assert_eq!(ranges.ranges.len(), 1);
+        assert!(ranges.contains(0, 100));
+    }
+
+    #[test]
+    fn insert_keeps_disjoint_ranges_separate()
+    {
+        let mut ranges = RangeSet::default();
+        ranges.insert(ByteRange { start: 0, end: 10 });
+        ranges.insert(ByteRange { start: 100, end: 110 });
+        assert_eq!(ranges.ranges.len(), 2);
+        assert!(!ranges.contains(0, 110));
+    }
+}
+
+/// Which download strategy `HttpSource` should use, mirroring librespot's
+/// two fetch strategies for remote audio.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FetchStrategy
+{
+    /// Read ahead of the cursor sequentially, as is typical during linear playback.
+    Streaming,
+    /// Issue a ranged request around the read cursor after a seek lands
+    /// outside the buffered region, instead of downloading everything in between.
+    RandomAccess
+}
+
+struct Shared
+{
+    data: Mutex<Vec<u8>>,
+    downloaded: Mutex<RangeSet>,
+    condvar: Condvar,
+    total_len: Option<u64>,
+    seekable: bool,
+    error: Mutex<Option<String>>
+}
+
+/// A Symphonia `MediaSource` backed by a remote HTTP resource.
+///
+/// Bytes are fetched in the background onto a shared buffer; `read` blocks
+/// until the requested bytes are present. Playback normally pulls bytes in
+/// [`FetchStrategy::Streaming`] mode; a [`Seek`] that lands outside the
+/// already-buffered region switches to [`FetchStrategy::RandomAccess`],
+/// issuing a ranged request around the new cursor instead of downloading
+/// everything in between.
+pub struct HttpSource
+{
+    shared: Arc<Shared>,
+    position: u64,
+    strategy: FetchStrategy,
+    request_tx: crossbeam::channel::Sender<FetchRequest>
+}
+
+impl HttpSource
+{
+    /// Opens `url`, inspecting `Accept-Ranges`/`Content-Length` to determine
+    /// seekability and total length, then spawns a background fetch thread.
+    pub fn new(url: String) -> anyhow::Result<Self>
+    {
+        let head = ureq::head(&url).call()?;
+        let seekable = head.header("Accept-Ranges").map(|v| v == "bytes").unwrap_or(false);
+        let total_len = head.header("Content-Length").and_then(|v| v.parse().ok());
+
+        let shared = Arc::new(Shared {
+            data: Mutex::new(vec![0u8; total_len.unwrap_or(0) as usize]),
+            downloaded: Mutex::new(RangeSet::default()),
+            condvar: Condvar::new(),
+            total_len,
+            seekable,
+            error: Mutex::new(None)
+        });
+
+        let (request_tx, request_rx) = crossbeam::channel::unbounded::<FetchRequest>();
+
+        let fetch_shared = shared.clone();
+        let fetch_url = url.clone();
+        let fetch_request_tx = request_tx.clone();
+        thread::spawn(move || Self::fetch_loop(fetch_url, fetch_shared, request_rx, fetch_request_tx));
+
+        // Kick off an initial sequential download of just the first chunk,
+        // not the whole file, so playback can start immediately; the fetch
+        // thread reads ahead automatically from here via `continuous`.
+        let first_chunk_end = total_len.map(|len| len.min(STREAM_CHUNK_BYTES)).unwrap_or(STREAM_CHUNK_BYTES);
+        request_tx.send(FetchRequest { range: ByteRange { start: 0, end: first_chunk_end }, continuous: true })?;
+
+        Ok(HttpSource {
+            shared,
+            position: 0,
+            strategy: FetchStrategy::Streaming,
+            request_tx
+        })
+    }
+
+    /// Services range requests on a background thread, writing fetched
+    /// bytes into the shared buffer and recording them in `downloaded`.
+    ///
+    /// When a `continuous` request finishes and more of the file remains,
+    /// the next chunk is enqueued automatically via `request_tx`, so
+    /// sequential playback keeps reading ahead without anyone having to
+    /// poll for it.
+    fn fetch_loop(
+        url: String,
+        shared: Arc<Shared>,
+        request_rx: crossbeam::channel::Receiver<FetchRequest>,
+        request_tx: crossbeam::channel::Sender<FetchRequest>
+    )
+    {
+        while let Ok(FetchRequest { range, continuous }) = request_rx.recv()
+        {
+            let request = ureq::get(&url)
+                .set("Range", &format!("bytes={}-{}", range.start,
+                    if range.end == u64::MAX { String::new() } else { (range.end - 1).to_string() }));
+
+            let response = match request.call() {
+                Ok(response) => response,
+                Err(err) => {
+                    *shared.error.lock().unwrap() = Some(err.to_string());
+                    shared.condvar.notify_all();
+                    continue;
+                }
+            };
+
+            let mut reader = response.into_reader();
+            let mut offset = range.start;
+            let mut chunk = [0u8; 64 * 1024];
+
+            loop {
+                let read = match reader.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(err) => {
+                        *shared.error.lock().unwrap() = Some(err.to_string());
+                        shared.condvar.notify_all();
+                        break;
+                    }
+                };
+
+                let mut data = shared.data.lock().unwrap();
+                let end = (offset as usize) + read;
+                if data.len() < end { data.resize(end, 0); }
+                data[offset as usize..end].copy_from_slice(&chunk[..read]);
+                drop(data);
+
+                shared.downloaded.lock().unwrap().insert(ByteRange { start: offset, end: offset as u64 + read as u64 });
+                offset += read as u64;
+                shared.condvar.notify_all();
+            }
+
+            if continuous {
+                let more_remains = shared.total_len.map(|len| offset < len).unwrap_or(true);
+                if more_remains {
+                    let next_end = shared.total_len
+                        .map(|len| len.min(offset + STREAM_CHUNK_BYTES))
+                        .unwrap_or(offset + STREAM_CHUNK_BYTES);
+                    let _ = request_tx.send(FetchRequest {
+                        range: ByteRange { start: offset, end: next_end },
+                        continuous: true
+                    });
+                }
+            }
+        }
+    }
+
+    /// Blocks until `[start, end)` has been downloaded, or an error/EOF surfaces.
+    ///
+    /// `end` is clamped to `total_len` (when known) before waiting, since no
+    /// fetch will ever reach past the end of the file; without the clamp a
+    /// read of the final, partial chunk would wait forever.
+    fn wait_for_range(&self, start: u64, end: u64) -> io::Result<()>
+    {
+        let end = self.shared.total_len.map(|total_len| end.min(total_len)).unwrap_or(end);
+
+        if let Some(total_len) = self.shared.total_len {
+            if start >= total_len { return Ok(()); }
+        }
+
+        let mut downloaded = self.shared.downloaded.lock().unwrap();
+
+        while !downloaded.contains(start, end)
+        {
+            if let Some(err) = self.shared.error.lock().unwrap().clone() {
+                return Err(io::Error::new(io::ErrorKind::Other, err));
+            }
+
+            downloaded = self.shared.condvar.wait(downloaded).unwrap();
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for HttpSource
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+    {
+        let want_end = self.shared.total_len
+            .map(|total_len| (self.position + buf.len() as u64).min(total_len))
+            .unwrap_or(self.position + buf.len() as u64);
+
+        // A `RandomAccess` seek only requested a one-shot range around the
+        // cursor. The first read that isn't already fully buffered switches
+        // back to `Streaming` and resumes continuous read-ahead from the
+        // cursor. In-flight requests are not tracked, so this also happens
+        // while that one-shot request is still downloading, and both
+        // requests may then fetch the same bytes.
+        if self.strategy == FetchStrategy::RandomAccess
+            && !self.shared.downloaded.lock().unwrap().contains(self.position, want_end)
+        {
+            self.strategy = FetchStrategy::Streaming;
+            let end = self.shared.total_len
+                .map(|len| len.min(self.position + STREAM_CHUNK_BYTES))
+                .unwrap_or(self.position + STREAM_CHUNK_BYTES);
+            let _ = self.request_tx.send(FetchRequest {
+                range: ByteRange { start: self.position, end },
+                continuous: true
+            });
+        }
+
+        self.wait_for_range(self.position, want_end)?;
+
+        let data = self.shared.data.lock().unwrap();
+        let available_end = want_end.min(data.len() as u64);
+        if available_end <= self.position { return Ok(0); }
+
+        let n = (available_end - self.position) as usize;
+        buf[..n].copy_from_slice(&data[self.position as usize..available_end as usize]);
+        self.position += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl Seek for HttpSource
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64>
+    {
+        if !self.shared.seekable {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "Source does not support seeking."));
+        }
+
+        let invalid_seek = || io::Error::new(io::ErrorKind::InvalidInput, "Resulting seek position is out of range.");
+
+        let new_position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(offset) => {
+                let total_len = self.shared.total_len
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "Unknown content length."))?;
+                total_len.checked_add_signed(offset).ok_or_else(invalid_seek)?
+            },
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset).ok_or_else(invalid_seek)?
+        };
+
+        let already_buffered = self.shared.downloaded.lock().unwrap().contains(new_position, new_position + 1);
+
+        // Only switch into random-access mode (and issue a new ranged
+        // request) when the seek lands outside what's already buffered.
+        // Unlike the continuous read-ahead used for sequential streaming,
+        // a random-access fetch is a one-shot range around the new cursor;
+        // `Read::read` switches back to `Streaming` (and resumes continuous
+        // read-ahead) on the first read that isn't already buffered.
+        if !already_buffered {
+            self.strategy = FetchStrategy::RandomAccess;
+            let end = self.shared.total_len
+                .map(|len| len.min(new_position + STREAM_CHUNK_BYTES))
+                .unwrap_or(new_position + STREAM_CHUNK_BYTES);
+            self.request_tx.send(FetchRequest { range: ByteRange { start: new_position, end }, continuous: false })
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        }
+
+        self.position = new_position;
+        Ok(self.position)
+    }
+}
+
+impl MediaSource for HttpSource
+{
+    fn is_seekable(&self) -> bool
+    {
+        self.shared.seekable
+    }
+
+    fn byte_len(&self) -> Option<u64>
+    {
+        self.shared.total_len
+    }
+}
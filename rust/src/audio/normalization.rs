@@ -0,0 +1,222 @@
+// This file is a part of simple_audio
+// Copyright (c) 2022-2023 Erikas Taroza <erikastaroza@gmail.com>
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public License as
+// published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with this program.
+// If not, see <https://www.gnu.org/licenses/>.
+
+use symphonia::core::{audio::AudioBuffer, meta::MetadataRevision};
+
+/// Which ReplayGain value to normalize to, mirroring librespot's
+/// `--normalisation-type auto`.
+#[derive(Clone, Copy, Default)]
+pub enum NormalizationMode
+{
+    /// Use the album gain when the track is part of an album, falling
+    /// back to the track gain otherwise.
+    #[default]
+    Auto,
+    Album,
+    Track
+}
+
+/// The `REPLAYGAIN_*` tags read off of a source, if present.
+#[derive(Clone, Copy, Default)]
+pub struct ReplayGain
+{
+    pub track_gain_db: Option<f64>,
+    pub track_peak: Option<f64>,
+    pub album_gain_db: Option<f64>,
+    pub album_peak: Option<f64>
+}
+
+impl ReplayGain
+{
+    /// Reads the `REPLAYGAIN_*` tags out of a Symphonia metadata revision.
+    pub fn parse(metadata: &MetadataRevision) -> Self
+    {
+        let mut replay_gain = ReplayGain::default();
+
+        for tag in metadata.tags()
+        {
+            let value = tag.value.to_string();
+            let parsed: Option<f64> = value
+                .trim_end_matches("dB")
+                .trim_end_matches("DB")
+                .trim()
+                .parse()
+                .ok();
+
+            match tag.key.to_ascii_uppercase().as_str() {
+                "REPLAYGAIN_TRACK_GAIN" => replay_gain.track_gain_db = parsed,
+                "REPLAYGAIN_TRACK_PEAK" => replay_gain.track_peak = parsed,
+                "REPLAYGAIN_ALBUM_GAIN" => replay_gain.album_gain_db = parsed,
+                "REPLAYGAIN_ALBUM_PEAK" => replay_gain.album_peak = parsed,
+                _ => ()
+            }
+        }
+
+        replay_gain
+    }
+
+    /// Picks the gain (dB) and peak for the given mode, falling back to
+    /// unity gain when the relevant tags are missing.
+    fn resolve(&self, mode: NormalizationMode) -> (f64, f64)
+    {
+        let (gain_db, peak) = match mode {
+            NormalizationMode::Track => (self.track_gain_db, self.track_peak),
+            NormalizationMode::Album => (self.album_gain_db, self.album_peak),
+            NormalizationMode::Auto => if self.album_gain_db.is_some() {
+                (self.album_gain_db, self.album_peak)
+            } else {
+                (self.track_gain_db, self.track_peak)
+            }
+        };
+
+        (gain_db.unwrap_or(0.0), peak.unwrap_or(1.0))
+    }
+}
+
+/// Settings controlling how much (if any) normalization is applied.
+#[derive(Clone, Copy)]
+pub struct NormalizationConfig
+{
+    pub mode: NormalizationMode,
+    /// Additional gain (dB) applied on top of the ReplayGain value, ex: to
+    /// target a louder/quieter reference level than the tags assume.
+    pub pregain_db: f64,
+    /// Overall output volume, applied as a final multiplier alongside gain.
+    pub master_volume: f32
+}
+
+impl Default for NormalizationConfig
+{
+    fn default() -> Self
+    {
+        NormalizationConfig {
+            mode: NormalizationMode::Auto,
+            pregain_db: 0.0,
+            master_volume: 1.0
+        }
+    }
+}
+
+impl NormalizationConfig
+{
+    /// Computes the linear sample multiplier for a given `ReplayGain`,
+    /// clamped by a simple peak limiter so the loudest sample in the file
+    /// never clips above full scale.
+    pub fn linear_gain(&self, replay_gain: &ReplayGain) -> f32
+    {
+        let (gain_db, peak) = replay_gain.resolve(self.mode);
+        let mut gain = 10f64.powf((gain_db + self.pregain_db) / 20.0);
+
+        // If applying `gain` would push the loudest sample past 1.0,
+        // scale it down so that sample lands at exactly full scale.
+        if gain * peak > 1.0 {
+            gain = 1.0 / peak;
+        }
+
+        (gain as f32) * self.master_volume
+    }
+}
+
+/// Multiplies every sample in `buffer` by `gain` in place.
+///
+/// Files without ReplayGain tags resolve to 0 dB with a peak of 1.0, so their
+/// `gain` is only the pregain (capped at 1.0 by the peak limiter) times the
+/// master volume.
+pub fn apply_gain(buffer: &mut AudioBuffer<f32>, gain: f32)
+{
+    if gain == 1.0 { return; }
+
+    for plane in buffer.planes_mut().planes() {
+        for sample in plane.iter_mut() {
+            *sample *= gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn replay_gain(track_gain_db: f64, track_peak: f64, album_gain_db: f64, album_peak: f64) -> ReplayGain
+    {
+        ReplayGain {
+            track_gain_db: Some(track_gain_db),
+            track_peak: Some(track_peak),
+            album_gain_db: Some(album_gain_db),
+            album_peak: Some(album_peak)
+        }
+    }
+
+    #[test]
+    fn unity_gain_without_tags()
+    {
+        let config = NormalizationConfig::default();
+        let gain = config.linear_gain(&ReplayGain::default());
+        assert_eq!(gain, 1.0);
+    }
+
+    #[test]
+    fn track_mode_uses_track_gain()
+    {
+        let config = NormalizationConfig { mode: NormalizationMode::Track, ..NormalizationConfig::default() };
+        let gain = config.linear_gain(&replay_gain(-6.0, 0.5, 6.0, 0.5));
+        let expected = 10f64.powf(-6.0 / 20.0) as f32;
+        assert!((gain - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn auto_mode_prefers_album_gain_when_present()
+    {
+        let config = NormalizationConfig::default();
+        let gain = config.linear_gain(&replay_gain(-6.0, 0.5, -3.0, 0.5));
+        let expected = 10f64.powf(-3.0 / 20.0) as f32;
+        assert!((gain - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn peak_limiter_prevents_clipping()
+    {
+        let config = NormalizationConfig { mode: NormalizationMode::Track, ..NormalizationConfig::default() };
+        // A large positive gain that would clip given the track's peak.
+        let gain = config.linear_gain(&replay_gain(12.0, 0.9, 0.0, 1.0));
+        let peak_after_gain = gain as f64 * 0.9;
+        assert!(peak_after_gain <= 1.0 + 1e-6);
+    }
+
+    #[test]
+    fn pregain_and_master_volume_are_applied()
+    {
+        let config = NormalizationConfig { mode: NormalizationMode::Track, pregain_db: 6.0, master_volume: 0.5 };
+        // A peak of 0.5 keeps the +6 dB pregain below the peak limiter.
+        let gain = config.linear_gain(&replay_gain(0.0, 0.5, 0.0, 0.5));
+        let expected = (10f64.powf(6.0 / 20.0) as f32) * 0.5;
+        assert!((gain - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn apply_gain_is_noop_at_unity()
+    {
+        let spec = symphonia::core::audio::SignalSpec::new(44_100, symphonia::core::audio::Channels::FRONT_LEFT);
+        let mut buffer = AudioBuffer::<f32>::new(4, spec);
+        buffer.render_reserved(Some(4));
+        for sample in buffer.chan_mut(0).iter_mut() { *sample = 0.5; }
+
+        apply_gain(&mut buffer, 1.0);
+
+        assert!(buffer.chan(0).iter().all(|&s| s == 0.5));
+    }
+}
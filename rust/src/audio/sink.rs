@@ -0,0 +1,236 @@
+// This file is a part of simple_audio
+// Copyright (c) 2022-2023 Erikas Taroza <erikastaroza@gmail.com>
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public License as
+// published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with this program.
+// If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Child, Command, Stdio}
+};
+
+use anyhow::Context;
+use symphonia::core::audio::{AudioBufferRef, SampleBuffer, SignalSpec};
+
+use super::cpal_output::CpalOutput;
+
+/// A destination for decoded audio samples.
+///
+/// `Decoder` writes every decoded packet to a `Box<dyn Sink>` instead of
+/// talking to `CpalOutput` directly, so playback can be routed to the
+/// system's default device, a file, or another process.
+pub trait Sink: Send
+{
+    /// Writes a decoded buffer to the sink.
+    fn write(&mut self, buffer: AudioBufferRef);
+    /// Flushes any samples that are still buffered.
+    fn flush(&mut self);
+    /// Resumes playback, if the sink supports pausing.
+    fn play(&mut self) -> anyhow::Result<()>;
+    /// Pauses playback, if the sink supports pausing.
+    fn pause(&mut self) -> anyhow::Result<()>;
+}
+
+/// Selects which `Sink` implementation `Decoder` should construct,
+/// mirroring librespot's `--backend` selection.
+#[derive(Clone)]
+pub enum SinkConfig
+{
+    /// Play through the system's default output device via `cpal`.
+    Cpal,
+    /// Write raw interleaved PCM samples to a file/fd (e.g. a named pipe to stdout).
+    Pipe(PathBuf),
+    /// Spawn a command and pipe PCM samples to its stdin (e.g. piping into `ffmpeg`).
+    Subprocess(String)
+}
+
+impl SinkConfig
+{
+    /// Builds the configured `Sink`, sized for the given stream spec.
+    pub fn build(&self, spec: SignalSpec, duration: u64) -> anyhow::Result<Box<dyn Sink>>
+    {
+        match self {
+            SinkConfig::Cpal => Ok(Box::new(CpalSink::new(spec, duration)?)),
+            SinkConfig::Pipe(path) => Ok(Box::new(PipeSink::new(path)?)),
+            SinkConfig::Subprocess(command) => Ok(Box::new(SubprocessSink::new(command)?))
+        }
+    }
+}
+
+/// The default sink. Plays through the system's output device via `cpal`.
+pub struct CpalSink
+{
+    output: CpalOutput
+}
+
+impl CpalSink
+{
+    fn new(spec: SignalSpec, duration: u64) -> anyhow::Result<Self>
+    {
+        Ok(CpalSink { output: CpalOutput::new(spec, duration)? })
+    }
+}
+
+impl Sink for CpalSink
+{
+    fn write(&mut self, buffer: AudioBufferRef)
+    {
+        self.output.write(buffer);
+    }
+
+    fn flush(&mut self)
+    {
+        self.output.flush();
+    }
+
+    fn play(&mut self) -> anyhow::Result<()>
+    {
+        Ok(self.output.stream.play()?)
+    }
+
+    fn pause(&mut self) -> anyhow::Result<()>
+    {
+        Ok(self.output.stream.pause()?)
+    }
+}
+
+/// Writes raw interleaved `f32` PCM to a file/fd, such as a pipe to stdout.
+///
+/// Useful for feeding a standalone consumer (ffmpeg, an ALSA loopback,
+/// a network streamer) that reads samples off of a well-known path.
+pub struct PipeSink
+{
+    file: std::fs::File
+}
+
+impl PipeSink
+{
+    fn new(path: &PathBuf) -> anyhow::Result<Self>
+    {
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .with_context(|| format!("Could not open pipe sink at {path:?}."))?;
+
+        Ok(PipeSink { file })
+    }
+
+    fn write_samples(&mut self, buffer: AudioBufferRef)
+    {
+        let mut sample_buf = SampleBuffer::<f32>::new(buffer.capacity() as u64, *buffer.spec());
+        sample_buf.copy_interleaved_ref(buffer);
+
+        if let Err(_) = self.file.write_all(bytemuck::cast_slice(sample_buf.samples())) {
+            // The reader went away (ex: a pipe with no listener).
+            // The error is dropped; later writes are attempted the same way.
+        }
+    }
+}
+
+impl Sink for PipeSink
+{
+    fn write(&mut self, buffer: AudioBufferRef)
+    {
+        self.write_samples(buffer);
+    }
+
+    fn flush(&mut self)
+    {
+        let _ = self.file.flush();
+    }
+
+    // A plain file/pipe has no concept of play/pause; writes simply stop.
+    fn play(&mut self) -> anyhow::Result<()>
+    {
+        Ok(())
+    }
+
+    fn pause(&mut self) -> anyhow::Result<()>
+    {
+        Ok(())
+    }
+}
+
+/// Spawns a command and pipes raw interleaved `f32` PCM to its stdin.
+///
+/// Lets users route decoded audio into an external tool, ex:
+/// `ffmpeg -f f32le -ar 44100 -ac 2 -i pipe:0 out.mp3`.
+pub struct SubprocessSink
+{
+    child: Child
+}
+
+impl SubprocessSink
+{
+    fn new(command: &str) -> anyhow::Result<Self>
+    {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().context("Subprocess sink command is empty.")?;
+
+        let child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Could not spawn subprocess sink `{command}`."))?;
+
+        Ok(SubprocessSink { child })
+    }
+
+    fn write_samples(&mut self, buffer: AudioBufferRef)
+    {
+        let Some(stdin) = self.child.stdin.as_mut() else { return; };
+
+        let mut sample_buf = SampleBuffer::<f32>::new(buffer.capacity() as u64, *buffer.spec());
+        sample_buf.copy_interleaved_ref(buffer);
+
+        // If the child's stdin is closed, the write error is ignored;
+        // nothing here checks whether the child has exited.
+        let _ = stdin.write_all(bytemuck::cast_slice(sample_buf.samples()));
+    }
+}
+
+impl Sink for SubprocessSink
+{
+    fn write(&mut self, buffer: AudioBufferRef)
+    {
+        self.write_samples(buffer);
+    }
+
+    fn flush(&mut self)
+    {
+        if let Some(stdin) = self.child.stdin.as_mut() {
+            let _ = stdin.flush();
+        }
+    }
+
+    // The subprocess itself has no play/pause hook; pausing is handled by
+    // `Decoder` simply not writing to the sink while paused.
+    fn play(&mut self) -> anyhow::Result<()>
+    {
+        Ok(())
+    }
+
+    fn pause(&mut self) -> anyhow::Result<()>
+    {
+        Ok(())
+    }
+}
+
+impl Drop for SubprocessSink
+{
+    fn drop(&mut self)
+    {
+        let _ = self.child.kill();
+    }
+}
@@ -0,0 +1,31 @@
+// This file is a part of simple_audio
+// Copyright (c) 2022-2023 Erikas Taroza <erikastaroza@gmail.com>
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public License as
+// published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with this program.
+// If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+
+use super::types::PlaybackState;
+
+lazy_static! {
+    static ref PLAYBACK_STATE_STREAM: RwLock<Option<PlaybackState>> = RwLock::new(None);
+}
+
+/// Sends the current `PlaybackState` to Dart.
+pub fn update_playback_state_stream(state: PlaybackState)
+{
+    *PLAYBACK_STATE_STREAM.write().unwrap() = Some(state);
+}
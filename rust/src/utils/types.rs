@@ -0,0 +1,84 @@
+// This file is a part of simple_audio
+// Copyright (c) 2022-2023 Erikas Taroza <erikastaroza@gmail.com>
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public License as
+// published by the Free Software Foundation, either version 3 of
+// the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with this program.
+// If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::{atomic::AtomicBool, RwLock};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use lazy_static::lazy_static;
+use symphonia::core::io::MediaSource;
+
+use crate::audio::{dsp::FadeConfig, normalization::NormalizationConfig, sink::SinkConfig};
+
+lazy_static! {
+    /// The sender/receiver pair used to send `ThreadMessage`s to the decoder thread.
+    pub static ref TXRX: RwLock<(Sender<ThreadMessage>, Receiver<ThreadMessage>)> = RwLock::new(unbounded());
+    /// The seek target, in milliseconds, requested by the last call to `seek()`.
+    pub static ref SEEK_TS: RwLock<Option<u64>> = RwLock::new(None);
+    pub static ref PROGRESS: RwLock<ProgressState> = RwLock::new(ProgressState { position: 0, duration: 0 });
+}
+
+pub static IS_PLAYING: AtomicBool = AtomicBool::new(false);
+pub static IS_LOOPING: AtomicBool = AtomicBool::new(false);
+pub static IS_FILE_PRELOADED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy)]
+pub struct ProgressState
+{
+    pub position: u64,
+    pub duration: u64
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackState
+{
+    Playing,
+    Paused,
+    Done
+}
+
+/// Messages sent from the public API to the decoder thread.
+pub enum ThreadMessage
+{
+    Dispose,
+    Open(Box<dyn MediaSource>),
+    /// Selects which `Sink` implementation the decoder writes to.
+    SetSink(SinkConfig),
+    /// Updates the ReplayGain / loudness normalization settings.
+    SetNormalization(NormalizationConfig),
+    /// Updates the crossfade/fade-in/fade-out durations.
+    SetFade(FadeConfig),
+    Play,
+    Pause,
+    Stop,
+    DeviceChanged,
+    Preload(Box<dyn MediaSource>),
+    PlayPreload,
+    /// Appends a single source to the gapless queue.
+    Enqueue(Box<dyn MediaSource>),
+    /// Replaces the entire gapless queue.
+    SetQueue(Vec<Box<dyn MediaSource>>)
+}
+
+/// Events sent from the decoder thread back to the public API.
+pub enum Callback
+{
+    DecodeError,
+    PlaybackLooped,
+    /// Sent when the gapless queue automatically advances to the next track.
+    TrackChanged,
+    /// Sent when a requested seek could not be performed.
+    SeekError
+}